@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+/// Opens `filename` for reading, transparently decompressing it based on its
+/// extension. `.gz` and `.bgz`/`.bgzf` are read as (multi-stream) gzip, `.zst`
+/// as zstd; anything else is read as plain text. Shared by both binaries so
+/// compressed FASTA/FASTQ input "just works" without a manual `zcat` step.
+pub fn open_input(filename: &str) -> Result<Box<dyn BufRead>> {
+    let file = File::open(filename)
+        .with_context(|| format!("Failed to open file: {}", filename))?;
+
+    let reader: Box<dyn Read> = match extension(filename) {
+        Some("gz") | Some("bgz") | Some("bgzf") => {
+            // BGZF is a sequence of standard gzip members, so a multi-stream
+            // gzip decoder reads it correctly without needing block offsets.
+            Box::new(flate2::read::MultiGzDecoder::new(file))
+        }
+        Some("zst") => Box::new(zstd::stream::read::Decoder::new(file)?),
+        _ => Box::new(file),
+    };
+
+    Ok(Box::new(BufReader::new(reader)))
+}
+
+/// Opens `filename` for writing, compressing based on its extension using the
+/// same scheme as [`open_input`]. Anything else is written as plain text.
+pub fn create_output(filename: &str) -> Result<Box<dyn Write>> {
+    let file = File::create(filename)
+        .with_context(|| format!("Failed to create output file: {}", filename))?;
+
+    let writer: Box<dyn Write> = match extension(filename) {
+        Some("gz") => Box::new(flate2::write::GzEncoder::new(file, flate2::Compression::default())),
+        Some("bgz") | Some("bgzf") => {
+            // Real BGZF: independently-compressed blocks with a `BC` extra
+            // subfield recording each block's size, so tools like tabix and
+            // samtools can seek into the output, not just decompress it.
+            Box::new(bgzip::BGZFWriter::new(file, bgzip::Compression::default()))
+        }
+        Some("zst") => Box::new(zstd::stream::write::Encoder::new(file, 0)?.auto_finish()),
+        _ => Box::new(file),
+    };
+
+    Ok(writer)
+}
+
+fn extension(filename: &str) -> Option<&str> {
+    Path::new(filename).extension().and_then(|ext| ext.to_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(extension: &str) {
+        let path = std::env::temp_dir().join(format!(
+            "seqdedupe-codec-test-{}-{}.{}",
+            std::process::id(),
+            extension,
+            extension
+        ));
+        let path = path.to_str().unwrap();
+
+        let mut writer = create_output(path).unwrap();
+        writeln!(writer, ">seq1").unwrap();
+        writeln!(writer, "ACGTACGT").unwrap();
+        drop(writer);
+
+        let mut contents = String::new();
+        open_input(path).unwrap().read_to_string(&mut contents).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(contents, ">seq1\nACGTACGT\n");
+    }
+
+    #[test]
+    fn gz_round_trips() {
+        roundtrip("gz");
+    }
+
+    #[test]
+    fn bgz_round_trips() {
+        roundtrip("bgz");
+    }
+
+    #[test]
+    fn bgzf_round_trips() {
+        roundtrip("bgzf");
+    }
+
+    #[test]
+    fn zst_round_trips() {
+        roundtrip("zst");
+    }
+
+    #[test]
+    fn plain_text_round_trips() {
+        roundtrip("fasta");
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::{HashMap, HashSet};
+use xxhash_rust::xxh3::xxh3_128;
+
+/// Tracks which sequences have already been seen using a 128-bit xxh3
+/// fingerprint instead of the sequence itself, cutting the memory footprint
+/// of exact-duplicate tracking from O(total sequence length) to O(record
+/// count). Collisions are astronomically unlikely but, since xxh3 is not
+/// cryptographic, `--verify` mode keeps the original bytes around to
+/// confirm a match before treating a record as a duplicate.
+pub struct SeenSet {
+    fingerprints: HashSet<u128>,
+    verify_cache: Option<HashMap<u128, Vec<String>>>,
+    representatives: Option<HashMap<u128, String>>,
+}
+
+impl SeenSet {
+    /// `track_representatives` additionally remembers, per fingerprint, the
+    /// header of the first record that claimed it, so a `--report` pass can
+    /// say which record a duplicate collapsed into.
+    pub fn new(verify: bool, track_representatives: bool) -> Self {
+        SeenSet {
+            fingerprints: HashSet::new(),
+            verify_cache: verify.then(HashMap::new),
+            representatives: track_representatives.then(HashMap::new),
+        }
+    }
+
+    /// Picks the canonical form to fingerprint: for DNA this is the
+    /// lexicographically smaller of `sequence` and its reverse complement,
+    /// so a read and its reverse complement collapse to a single entry
+    /// instead of two. Both sides are compared and fingerprinted in upper
+    /// case, since soft-masked (lowercase) bases are the same sequence as
+    /// their upper-case twin, not a different one, and an unnormalized
+    /// comparison can pick different "canonical" forms for two records that
+    /// only differ in case.
+    pub fn canonical(sequence: &str, reverse_complement: &str, is_dna: bool) -> String {
+        if !is_dna {
+            return sequence.to_string();
+        }
+        let sequence = sequence.to_ascii_uppercase();
+        let reverse_complement = reverse_complement.to_ascii_uppercase();
+        if reverse_complement < sequence {
+            reverse_complement
+        } else {
+            sequence
+        }
+    }
+
+    pub fn contains(&self, sequence: &str) -> bool {
+        let fp = fingerprint(sequence);
+        if !self.fingerprints.contains(&fp) {
+            return false;
+        }
+        match &self.verify_cache {
+            Some(cache) => cache.get(&fp).is_some_and(|seen| seen.iter().any(|s| s == sequence)),
+            None => true,
+        }
+    }
+
+    /// Returns the header of the record that first claimed `sequence`'s
+    /// fingerprint, if representative tracking is enabled.
+    pub fn representative(&self, sequence: &str) -> Option<&str> {
+        let fp = fingerprint(sequence);
+        self.representatives.as_ref()?.get(&fp).map(String::as_str)
+    }
+
+    pub fn insert(&mut self, sequence: &str, header: &str) {
+        let fp = fingerprint(sequence);
+        self.fingerprints.insert(fp);
+        if let Some(cache) = &mut self.verify_cache {
+            // Keep every sequence sharing this fingerprint, so a colliding
+            // record does not evict the bytes of the one stored before it.
+            cache.entry(fp).or_default().push(sequence.to_string());
+        }
+        if let Some(reps) = &mut self.representatives {
+            reps.entry(fp).or_insert_with(|| header.to_string());
+        }
+    }
+}
+
+fn fingerprint(sequence: &str) -> u128 {
+    xxh3_128(sequence.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canonical_collapses_case_for_dna() {
+        // A soft-masked (lowercase) record and its all-upper-case twin are
+        // the same sequence in the same orientation, so they must pick the
+        // same canonical form.
+        assert_eq!(
+            SeenSet::canonical("acgtacgt", "ACGTACGT", true),
+            SeenSet::canonical("ACGTACGT", "acgtacgt", true),
+        );
+    }
+
+    #[test]
+    fn canonical_still_picks_the_lexicographically_smaller_strand() {
+        // "AAAA"'s reverse complement is "TTTT"; "AAAA" sorts first.
+        assert_eq!(SeenSet::canonical("AAAA", "TTTT", true), "AAAA");
+        assert_eq!(SeenSet::canonical("TTTT", "AAAA", true), "AAAA");
+    }
+
+    #[test]
+    fn canonical_is_case_sensitive_when_not_dna() {
+        assert_eq!(SeenSet::canonical("acgtacgt", "", false), "acgtacgt");
+    }
+
+    #[test]
+    fn seen_set_treats_mixed_case_dna_duplicates_as_duplicates() {
+        let mut seen = SeenSet::new(false, false);
+        let canonical = SeenSet::canonical("acgtacgt", "ACGTACGT", true);
+        seen.insert(&canonical, ">seq1");
+
+        let duplicate_canonical = SeenSet::canonical("ACGTACGT", "acgtacgt", true);
+        assert!(seen.contains(&duplicate_canonical));
+    }
+}
@@ -1,55 +1,104 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use anyhow::{Context, Result};
-use std::collections::HashSet;
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
+use std::io::{BufRead, Write};
+use std::time::Instant;
 use chrono::Utc;
 
+mod codec;
+mod dedup;
+mod report;
+mod suffix_automaton;
+use codec::{create_output, open_input};
+use dedup::SeenSet;
+use report::{Report, Summary};
+use suffix_automaton::SuffixAutomaton;
+
 #[derive(Parser)]
 #[command(name = "seqdedupe")]
-#[command(about = "Remove duplicate and substring sequences from FASTA files")]
+#[command(about = "Remove duplicate and substring sequences from FASTA/FASTQ files")]
 struct Args {
-    #[arg(help = "Input FASTA file")]
+    #[arg(help = "Input FASTA/FASTQ file")]
     input: String,
-    
+
     #[arg(short, long, help = "Output file (stdout if not specified)")]
     output: Option<String>,
-    
+
     #[arg(short, long, help = "Treat as DNA sequences (check reverse complements)")]
     dna: bool,
-    
+
     #[arg(short, long, help = "Remove substring sequences (slower for large files)")]
     substring: bool,
+
+    #[arg(long, value_enum, default_value = "auto", help = "Input format: auto-detect from the first byte, fasta, or fastq")]
+    format: Format,
+
+    #[arg(long, help = "On a fingerprint collision, compare actual sequence bytes to guarantee correctness")]
+    verify: bool,
+
+    #[arg(long, requires = "dna", help = "Normalize DNA records to their canonical (lexicographically smaller) strand and annotate the header with strand=+/-")]
+    canonical: bool,
+
+    #[arg(long, help = "Write a per-record dedup report (TSV, or JSON if the path ends in .json)")]
+    report: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Auto,
+    Fasta,
+    Fastq,
 }
 
 #[derive(Debug, Clone)]
 struct FastaRecord {
     header: String,
     sequence: String,
+    quality: Option<String>,
 }
 
 fn timestamp() -> String {
     Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string()
 }
 
+fn get_memory_usage() -> String {
+    #[cfg(target_os = "linux")]
+    {
+        if let Ok(contents) = std::fs::read_to_string("/proc/self/status") {
+            for line in contents.lines() {
+                if line.starts_with("VmRSS:") {
+                    return line.trim().to_string();
+                }
+            }
+        }
+    }
+    "Memory usage: N/A".to_string()
+}
+
+fn detect_format(filename: &str) -> Result<Format> {
+    let mut reader = open_input(filename)?;
+    match reader.fill_buf()?.first() {
+        Some(b'@') => Ok(Format::Fastq),
+        _ => Ok(Format::Fasta),
+    }
+}
+
 fn parse_fasta(filename: &str) -> Result<Vec<FastaRecord>> {
-    let file = File::open(filename)
-        .with_context(|| format!("Failed to open file: {}", filename))?;
-    let reader = BufReader::new(file);
-    
+    let reader = open_input(filename)?;
+
     let mut records = Vec::new();
     let mut current_header = String::new();
     let mut current_sequence = String::new();
-    
+
     for line in reader.lines() {
         let line = line?;
         let line = line.trim();
-        
+
         if line.starts_with('>') {
             if !current_header.is_empty() {
                 records.push(FastaRecord {
                     header: current_header.clone(),
                     sequence: current_sequence.clone(),
+                    quality: None,
                 });
             }
             current_header = line.to_string();
@@ -58,135 +107,294 @@ fn parse_fasta(filename: &str) -> Result<Vec<FastaRecord>> {
             current_sequence.push_str(line);
         }
     }
-    
+
     if !current_header.is_empty() {
         records.push(FastaRecord {
             header: current_header,
             sequence: current_sequence,
+            quality: None,
+        });
+    }
+
+    Ok(records)
+}
+
+fn parse_fastq(filename: &str) -> Result<Vec<FastaRecord>> {
+    let reader = open_input(filename)?;
+
+    let mut records = Vec::new();
+    let mut lines = reader.lines();
+
+    while let Some(header) = lines.next() {
+        let header = header?;
+        let header = header.trim();
+        if header.is_empty() {
+            continue;
+        }
+        let sequence = lines
+            .next()
+            .context("Truncated FASTQ record: missing sequence line")??
+            .trim()
+            .to_string();
+        let plus = lines
+            .next()
+            .context("Truncated FASTQ record: missing '+' line")??;
+        if !plus.trim().starts_with('+') {
+            anyhow::bail!("Malformed FASTQ record: expected '+' line, got {:?}", plus);
+        }
+        let quality = lines
+            .next()
+            .context("Truncated FASTQ record: missing quality line")??
+            .trim()
+            .to_string();
+
+        records.push(FastaRecord {
+            header: header.to_string(),
+            sequence,
+            quality: Some(quality),
         });
     }
-    
+
     Ok(records)
 }
 
+fn parse_records(filename: &str, format: Format) -> Result<Vec<FastaRecord>> {
+    let format = match format {
+        Format::Auto => detect_format(filename)?,
+        explicit => explicit,
+    };
+
+    match format {
+        Format::Fasta => parse_fasta(filename),
+        Format::Fastq => parse_fastq(filename),
+        Format::Auto => unreachable!("format was resolved above"),
+    }
+}
+
+/// Complements each base and reverses the result, preserving the case of
+/// each letter so a reverse-complemented soft-masked (lowercase) region
+/// stays soft-masked instead of silently losing its masking.
 fn reverse_complement(sequence: &str) -> String {
     sequence
         .chars()
         .rev()
-        .map(|c| match c.to_ascii_uppercase() {
+        .map(|c| match c {
             'A' => 'T',
             'T' => 'A',
             'G' => 'C',
             'C' => 'G',
             'N' => 'N',
+            'a' => 't',
+            't' => 'a',
+            'g' => 'c',
+            'c' => 'g',
+            'n' => 'n',
             '-' => '-',
             other => other,
         })
         .collect()
 }
 
-fn remove_exact_duplicates(records: Vec<FastaRecord>, is_dna: bool) -> Vec<FastaRecord> {
-    let mut seen_sequences = HashSet::new();
+fn remove_exact_duplicates(
+    records: Vec<FastaRecord>,
+    is_dna: bool,
+    verify: bool,
+    canonical: bool,
+    report: &mut Option<Report>,
+) -> Vec<FastaRecord> {
+    let mut seen = SeenSet::new(verify, report.is_some());
     let mut unique_records = Vec::new();
-    
-    for record in records {
-        let mut is_duplicate = false;
-        
-        if seen_sequences.contains(&record.sequence) {
-            is_duplicate = true;
-        }
-        
-        if is_dna && !is_duplicate {
-            let rev_comp = reverse_complement(&record.sequence);
-            if seen_sequences.contains(&rev_comp) {
-                is_duplicate = true;
+
+    for mut record in records {
+        let rev_comp = if is_dna {
+            reverse_complement(&record.sequence)
+        } else {
+            String::new()
+        };
+        let canonical_seq = SeenSet::canonical(&record.sequence, &rev_comp, is_dna);
+        // Compared upper case, since --dna fingerprinting and canonical
+        // selection already normalize case: a lowercase (soft-masked) record
+        // that happens to be the canonical forward orientation must still
+        // count as "kept forward", not get misdetected as a strand flip.
+        let kept_forward = !is_dna || canonical_seq == record.sequence.to_ascii_uppercase();
+
+        if seen.contains(&canonical_seq) {
+            if let Some(report) = report.as_mut() {
+                let representative = seen.representative(&canonical_seq).unwrap_or("?").to_string();
+                let reason = if kept_forward { "exact duplicate" } else { "reverse-complement duplicate" };
+                report.removed(&record.header, reason, &representative);
             }
+            continue;
         }
-        
-        if !is_duplicate {
-            seen_sequences.insert(record.sequence.clone());
-            if is_dna {
-                seen_sequences.insert(reverse_complement(&record.sequence));
+
+        if canonical && is_dna {
+            if !kept_forward {
+                record.sequence = rev_comp;
             }
-            unique_records.push(record);
+            record.header = format!("{} strand={}", record.header, if kept_forward { "+" } else { "-" });
         }
+
+        // Insert with the final header, after any --canonical annotation, so
+        // a later duplicate's representative points at the header that is
+        // actually written out (and that shows up in a --report "kept" row).
+        seen.insert(&canonical_seq, &record.header);
+
+        unique_records.push(record);
     }
-    
+
     unique_records
 }
 
-fn remove_substring_sequences(mut records: Vec<FastaRecord>, is_dna: bool) -> Vec<FastaRecord> {
-    records.sort_by(|a, b| b.sequence.len().cmp(&a.sequence.len()));
-    
+fn remove_substring_sequences(
+    mut records: Vec<FastaRecord>,
+    is_dna: bool,
+    report: &mut Option<Report>,
+) -> Vec<FastaRecord> {
+    records.sort_by_key(|record| std::cmp::Reverse(record.sequence.len()));
+
+    let mut automaton = SuffixAutomaton::new();
     let mut kept_records: Vec<FastaRecord> = Vec::new();
-    
-    for i in 0..records.len() {
-        let mut is_substring = false;
-        let current_seq = &records[i].sequence;
-        
-        for j in 0..kept_records.len() {
-            let longer_seq = &kept_records[j].sequence;
-            
-            if longer_seq.contains(current_seq) {
-                is_substring = true;
-                break;
+
+    for record in records {
+        let current_seq = &record.sequence;
+        let rev_comp = is_dna.then(|| reverse_complement(current_seq));
+
+        let mut owner = automaton.find_owner(current_seq);
+        if owner.is_none() {
+            if let Some(rev_comp) = &rev_comp {
+                owner = automaton.find_owner(rev_comp);
             }
-            
-            if is_dna {
-                let rev_comp = reverse_complement(current_seq);
-                if longer_seq.contains(&rev_comp) {
-                    is_substring = true;
-                    break;
-                }
+        }
+
+        if let Some(representative) = owner {
+            if let Some(report) = report.as_mut() {
+                report.removed(&record.header, "substring", representative);
             }
+            continue;
         }
-        
-        if !is_substring {
-            kept_records.push(records[i].clone());
+
+        automaton.insert(current_seq, &record.header);
+        if let Some(rev_comp) = &rev_comp {
+            automaton.insert(rev_comp, &record.header);
         }
+        kept_records.push(record);
     }
-    
+
     kept_records
 }
 
 fn write_fasta(records: &[FastaRecord], output: Option<&str>) -> Result<()> {
     if let Some(filename) = output {
-        let mut file = File::create(filename)
-            .with_context(|| format!("Failed to create output file: {}", filename))?;
-        
+        let mut writer = create_output(filename)?;
+
         for record in records {
-            writeln!(file, "{}", record.header)?;
-            writeln!(file, "{}", record.sequence)?;
+            write_record(&mut writer, record)?;
         }
     } else {
+        let stdout = std::io::stdout();
+        let mut handle = stdout.lock();
         for record in records {
-            println!("{}", record.header);
-            println!("{}", record.sequence);
+            write_record(&mut handle, record)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_record(writer: &mut impl Write, record: &FastaRecord) -> Result<()> {
+    match &record.quality {
+        Some(quality) => {
+            writeln!(writer, "{}", record.header)?;
+            writeln!(writer, "{}", record.sequence)?;
+            writeln!(writer, "+")?;
+            writeln!(writer, "{}", quality)?;
+        }
+        None => {
+            writeln!(writer, "{}", record.header)?;
+            writeln!(writer, "{}", record.sequence)?;
         }
     }
-    
     Ok(())
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
-    
-    eprintln!("[{}] Reading FASTA file: {}", timestamp(), args.input);
-    let records = parse_fasta(&args.input)?;
-    eprintln!("[{}] Found {} sequences", timestamp(), records.len());
-    
+    let start = Instant::now();
+
+    let mut report = args.report.as_ref().map(|_| Report::new());
+
+    eprintln!("[{}] Reading file: {}", timestamp(), args.input);
+    let records = parse_records(&args.input, args.format)?;
+    let total_input = records.len();
+    eprintln!("[{}] Found {} sequences", timestamp(), total_input);
+
     eprintln!("[{}] Removing exact duplicates...", timestamp());
-    let mut final_records = remove_exact_duplicates(records, args.dna);
+    let mut final_records = remove_exact_duplicates(records, args.dna, args.verify, args.canonical, &mut report);
     eprintln!("[{}] After removing exact duplicates: {} sequences", timestamp(), final_records.len());
-    
+
     if args.substring {
         eprintln!("[{}] Removing substring sequences...", timestamp());
-        final_records = remove_substring_sequences(final_records, args.dna);
+        final_records = remove_substring_sequences(final_records, args.dna, &mut report);
         eprintln!("[{}] After removing substring sequences: {} sequences", timestamp(), final_records.len());
     }
-    
+
     eprintln!("[{}] Final result: {} unique sequences", timestamp(), final_records.len());
     write_fasta(&final_records, args.output.as_deref())?;
-    
+
+    if let (Some(report), Some(report_path)) = (report.as_mut(), args.report.as_deref()) {
+        for record in &final_records {
+            report.kept(&record.header);
+        }
+        let summary = Summary {
+            total_input,
+            total_kept: final_records.len(),
+            total_removed: total_input - final_records.len(),
+            memory_usage: get_memory_usage(),
+            elapsed_secs: start.elapsed().as_secs_f64(),
+        };
+        report.write(report_path, &summary)?;
+        eprintln!("[{}] Wrote dedup report to {}", timestamp(), report_path);
+    }
+
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(header: &str, sequence: &str) -> FastaRecord {
+        FastaRecord {
+            header: header.to_string(),
+            sequence: sequence.to_string(),
+            quality: None,
+        }
+    }
+
+    #[test]
+    fn canonical_does_not_mistake_case_for_a_strand_flip() {
+        // seq2 is seq1's exact upper-case twin, not its reverse complement:
+        // --canonical must drop seq2 as a duplicate without annotating seq1
+        // as strand=- or discarding its soft-masked (lowercase) casing.
+        let records = vec![record(">seq1", "acgtacgt"), record(">seq2", "ACGTACGT")];
+        let mut report = None;
+
+        let kept = remove_exact_duplicates(records, true, false, true, &mut report);
+
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].header, ">seq1 strand=+");
+        assert_eq!(kept[0].sequence, "acgtacgt");
+    }
+
+    #[test]
+    fn canonical_still_annotates_a_real_strand_flip() {
+        let records = vec![record(">seq1", "TTTT"), record(">seq2", "AAAA")];
+        let mut report = None;
+
+        let kept = remove_exact_duplicates(records, true, false, true, &mut report);
+
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].header, ">seq1 strand=-");
+        assert_eq!(kept[0].sequence, "AAAA");
+    }
+}
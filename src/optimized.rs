@@ -1,55 +1,90 @@
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use anyhow::{Context, Result};
-use std::collections::HashSet;
-use std::fs::File;
-use std::io::{BufRead, BufReader, Write};
-use std::sync::{Arc, Mutex};
+use std::io::{BufRead, Lines, Write};
+use std::time::Instant;
 use chrono::Utc;
 use rayon::prelude::*;
 
+mod codec;
+mod dedup;
+mod report;
+mod suffix_automaton;
+use codec::{create_output, open_input};
+use dedup::SeenSet;
+use report::{Report, Summary};
+use suffix_automaton::SuffixAutomaton;
+
 #[derive(Parser)]
 #[command(name = "seqdedupe")]
-#[command(about = "Remove duplicate and substring sequences from FASTA files (memory optimized)")]
+#[command(about = "Remove duplicate and substring sequences from FASTA/FASTQ files (memory optimized)")]
 struct Args {
-    #[arg(help = "Input FASTA file")]
+    #[arg(help = "Input FASTA/FASTQ file")]
     input: String,
-    
+
     #[arg(short, long, help = "Output file (stdout if not specified)")]
     output: Option<String>,
-    
+
     #[arg(short, long, help = "Treat as DNA sequences (check reverse complements)")]
     dna: bool,
-    
+
     #[arg(short, long, help = "Remove substring sequences (slower for large files)")]
     substring: bool,
-    
+
     #[arg(long, help = "Batch size for processing (default 10000)")]
     batch_size: Option<usize>,
-    
+
     #[arg(long, help = "Number of CPU cores to use (default: half of available cores)")]
     cores: Option<usize>,
+
+    #[arg(long, value_enum, default_value = "auto", help = "Input format: auto-detect from the first byte, fasta, or fastq")]
+    format: Format,
+
+    #[arg(long, conflicts_with = "substring", help = "On a fingerprint collision, compare actual sequence bytes to guarantee correctness (not supported with --substring)")]
+    verify: bool,
+
+    #[arg(long, requires = "dna", conflicts_with = "substring", help = "Normalize DNA records to their canonical (lexicographically smaller) strand and annotate the header with strand=+/- (not supported with --substring)")]
+    canonical: bool,
+
+    #[arg(long, help = "Write a per-record dedup report (TSV, or JSON if the path ends in .json)")]
+    report: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Auto,
+    Fasta,
+    Fastq,
 }
 
 #[derive(Debug, Clone)]
 struct FastaRecord {
     header: String,
     sequence: String,
+    quality: Option<String>,
 }
 
 fn timestamp() -> String {
     Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string()
 }
 
+/// Complements each base and reverses the result, preserving the case of
+/// each letter so a reverse-complemented soft-masked (lowercase) region
+/// stays soft-masked instead of silently losing its masking.
 fn reverse_complement(sequence: &str) -> String {
     sequence
         .chars()
         .rev()
-        .map(|c| match c.to_ascii_uppercase() {
+        .map(|c| match c {
             'A' => 'T',
             'T' => 'A',
             'G' => 'C',
             'C' => 'G',
             'N' => 'N',
+            'a' => 't',
+            't' => 'a',
+            'g' => 'c',
+            'c' => 'g',
+            'n' => 'n',
             '-' => '-',
             other => other,
         })
@@ -70,291 +105,386 @@ fn get_memory_usage() -> String {
     "Memory usage: N/A".to_string()
 }
 
+fn detect_format(filename: &str) -> Result<Format> {
+    let mut reader = open_input(filename)?;
+    match reader.fill_buf()?.first() {
+        Some(b'@') => Ok(Format::Fastq),
+        _ => Ok(Format::Fasta),
+    }
+}
+
+fn resolve_format(filename: &str, format: Format) -> Result<Format> {
+    match format {
+        Format::Auto => detect_format(filename),
+        explicit => Ok(explicit),
+    }
+}
+
+/// Pulls one `FastaRecord` at a time off a buffered reader, understanding both
+/// wrapped multi-line FASTA and 4-line-per-record FASTQ, so the streaming and
+/// parallel passes below don't need to care which format they were handed.
+struct RecordReader<R: BufRead> {
+    lines: Lines<R>,
+    format: Format,
+    pending_header: Option<String>,
+}
+
+impl<R: BufRead> RecordReader<R> {
+    fn new(reader: R, format: Format) -> Self {
+        RecordReader {
+            lines: reader.lines(),
+            format,
+            pending_header: None,
+        }
+    }
+
+    fn next_record(&mut self) -> Result<Option<FastaRecord>> {
+        match self.format {
+            Format::Fastq => self.next_fastq_record(),
+            _ => self.next_fasta_record(),
+        }
+    }
+
+    fn next_fasta_record(&mut self) -> Result<Option<FastaRecord>> {
+        let mut header = self.pending_header.take();
+        let mut sequence = String::new();
+
+        for line in self.lines.by_ref() {
+            let line = line?;
+            let line = line.trim();
+
+            if line.starts_with('>') {
+                if header.is_some() {
+                    self.pending_header = Some(line.to_string());
+                    break;
+                }
+                header = Some(line.to_string());
+            } else if !line.is_empty() {
+                sequence.push_str(line);
+            }
+        }
+
+        Ok(header.map(|header| FastaRecord {
+            header,
+            sequence,
+            quality: None,
+        }))
+    }
+
+    fn next_fastq_record(&mut self) -> Result<Option<FastaRecord>> {
+        let header = loop {
+            match self.lines.next() {
+                Some(line) => {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    break line.trim().to_string();
+                }
+                None => return Ok(None),
+            }
+        };
+
+        let sequence = self
+            .lines
+            .next()
+            .context("Truncated FASTQ record: missing sequence line")??
+            .trim()
+            .to_string();
+        let plus = self
+            .lines
+            .next()
+            .context("Truncated FASTQ record: missing '+' line")??;
+        if !plus.trim().starts_with('+') {
+            anyhow::bail!("Malformed FASTQ record: expected '+' line, got {:?}", plus);
+        }
+        let quality = self
+            .lines
+            .next()
+            .context("Truncated FASTQ record: missing quality line")??
+            .trim()
+            .to_string();
+
+        Ok(Some(FastaRecord {
+            header,
+            sequence,
+            quality: Some(quality),
+        }))
+    }
+}
+
+fn write_record(writer: &mut impl Write, record: &FastaRecord) -> Result<()> {
+    match &record.quality {
+        Some(quality) => {
+            writeln!(writer, "{}", record.header)?;
+            writeln!(writer, "{}", record.sequence)?;
+            writeln!(writer, "+")?;
+            writeln!(writer, "{}", quality)?;
+        }
+        None => {
+            writeln!(writer, "{}", record.header)?;
+            writeln!(writer, "{}", record.sequence)?;
+        }
+    }
+    Ok(())
+}
+
 // Streaming approach - process file in batches to reduce memory
-fn process_streaming_duplicates(filename: &str, output_file: Option<&str>, is_dna: bool, batch_size: usize) -> Result<()> {
-    let file = File::open(filename)?;
-    let reader = BufReader::new(file);
-    
-    let mut seen_sequences = HashSet::new();
-    let mut current_header = String::new();
-    let mut current_sequence = String::new();
+#[allow(clippy::too_many_arguments)]
+fn process_streaming_duplicates(
+    filename: &str,
+    output_file: Option<&str>,
+    is_dna: bool,
+    batch_size: usize,
+    format: Format,
+    verify: bool,
+    canonical: bool,
+    report_path: Option<&str>,
+) -> Result<()> {
+    let start = Instant::now();
+    let format = resolve_format(filename, format)?;
+    let reader = open_input(filename)?;
+    let mut record_reader = RecordReader::new(reader, format);
+
+    let mut seen = SeenSet::new(verify, report_path.is_some());
+    let mut report = report_path.map(|_| Report::new());
     let mut batch_records = Vec::new();
     let mut total_processed = 0;
     let mut total_kept = 0;
-    
+
     // Setup output writer
     let mut output_writer: Box<dyn Write> = if let Some(output_path) = output_file {
-        Box::new(File::create(output_path)?)
+        create_output(output_path)?
     } else {
         Box::new(std::io::stdout())
     };
-    
-    let mut line_count = 0;
-    for line in reader.lines() {
-        let line = line?;
-        let line = line.trim();
-        line_count += 1;
-        
-        if line_count % 100000 == 0 {
-            eprintln!("[{}] Processed {} lines, {} sequences kept, {}", 
-                     timestamp(), line_count, total_kept, get_memory_usage());
+
+    let mut record_count = 0;
+    while let Some(record) = record_reader.next_record()? {
+        batch_records.push(record);
+        record_count += 1;
+
+        if record_count % 100000 == 0 {
+            eprintln!("[{}] Read {} records, {} sequences kept, {}",
+                     timestamp(), record_count, total_kept, get_memory_usage());
         }
-        
-        if line.starts_with('>') {
-            // Process previous record
-            if !current_header.is_empty() {
-                batch_records.push(FastaRecord {
-                    header: current_header.clone(),
-                    sequence: current_sequence.clone(),
-                });
-                
-                // Process batch when full
-                if batch_records.len() >= batch_size {
-                    let (kept, processed) = process_batch(&mut batch_records, &mut seen_sequences, is_dna, &mut output_writer)?;
-                    total_kept += kept;
-                    total_processed += processed;
-                    batch_records.clear();
-                }
-            }
-            current_header = line.to_string();
-            current_sequence.clear();
-        } else if !line.is_empty() {
-            current_sequence.push_str(line);
+
+        if batch_records.len() >= batch_size {
+            let (kept, processed) = process_batch(&mut batch_records, &mut seen, is_dna, canonical, &mut output_writer, &mut report)?;
+            total_kept += kept;
+            total_processed += processed;
+            batch_records.clear();
         }
     }
-    
-    // Process final record and batch
-    if !current_header.is_empty() {
-        batch_records.push(FastaRecord {
-            header: current_header,
-            sequence: current_sequence,
-        });
-    }
-    
+
     if !batch_records.is_empty() {
-        let (kept, processed) = process_batch(&mut batch_records, &mut seen_sequences, is_dna, &mut output_writer)?;
+        let (kept, processed) = process_batch(&mut batch_records, &mut seen, is_dna, canonical, &mut output_writer, &mut report)?;
         total_kept += kept;
         total_processed += processed;
     }
-    
-    eprintln!("[{}] Final: {} sequences processed, {} unique kept", 
+
+    eprintln!("[{}] Final: {} sequences processed, {} unique kept",
              timestamp(), total_processed, total_kept);
-    
+
+    if let (Some(report), Some(report_path)) = (&report, report_path) {
+        let summary = Summary {
+            total_input: total_processed,
+            total_kept,
+            total_removed: total_processed - total_kept,
+            memory_usage: get_memory_usage(),
+            elapsed_secs: start.elapsed().as_secs_f64(),
+        };
+        report.write(report_path, &summary)?;
+        eprintln!("[{}] Wrote dedup report to {}", timestamp(), report_path);
+    }
+
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn process_batch(
-    batch: &mut Vec<FastaRecord>, 
-    seen_sequences: &mut HashSet<String>, 
+    batch: &mut Vec<FastaRecord>,
+    seen: &mut SeenSet,
     is_dna: bool,
-    output_writer: &mut Box<dyn Write>
+    canonical: bool,
+    output_writer: &mut Box<dyn Write>,
+    report: &mut Option<Report>,
 ) -> Result<(usize, usize)> {
     let mut kept_count = 0;
     let processed_count = batch.len();
-    
-    for record in batch.drain(..) {
-        let sequence = &record.sequence;
-        let mut is_duplicate = false;
-        
-        // Check if sequence already seen
-        if seen_sequences.contains(sequence) {
-            is_duplicate = true;
-        }
-        
-        // For DNA, also check reverse complement
-        if is_dna && !is_duplicate {
-            let rev_comp = reverse_complement(sequence);
-            if seen_sequences.contains(&rev_comp) {
-                is_duplicate = true;
+
+    for mut record in batch.drain(..) {
+        let rev_comp = if is_dna {
+            reverse_complement(&record.sequence)
+        } else {
+            String::new()
+        };
+        let canonical_seq = SeenSet::canonical(&record.sequence, &rev_comp, is_dna);
+        // Compared upper case, since --dna fingerprinting and canonical
+        // selection already normalize case: a lowercase (soft-masked) record
+        // that happens to be the canonical forward orientation must still
+        // count as "kept forward", not get misdetected as a strand flip.
+        let kept_forward = !is_dna || canonical_seq == record.sequence.to_ascii_uppercase();
+
+        if seen.contains(&canonical_seq) {
+            if let Some(report) = report.as_mut() {
+                let representative = seen.representative(&canonical_seq).unwrap_or("?").to_string();
+                let reason = if kept_forward { "exact duplicate" } else { "reverse-complement duplicate" };
+                report.removed(&record.header, reason, &representative);
             }
+            continue;
         }
-        
-        if !is_duplicate {
-            seen_sequences.insert(sequence.clone());
-            if is_dna {
-                seen_sequences.insert(reverse_complement(sequence));
+
+        if canonical && is_dna {
+            if !kept_forward {
+                record.sequence = rev_comp;
             }
-            
-            // Write immediately to reduce memory usage
-            writeln!(output_writer, "{}", record.header)?;
-            writeln!(output_writer, "{}", record.sequence)?;
-            kept_count += 1;
+            record.header = format!("{} strand={}", record.header, if kept_forward { "+" } else { "-" });
+        }
+
+        // Insert with the final header, after any --canonical annotation, so
+        // a later duplicate's representative points at the header that is
+        // actually written out (and that shows up in a --report "kept" row).
+        seen.insert(&canonical_seq, &record.header);
+
+        if let Some(report) = report.as_mut() {
+            report.kept(&record.header);
         }
+
+        // Write immediately to reduce memory usage
+        write_record(output_writer, &record)?;
+        kept_count += 1;
     }
-    
+
     Ok((kept_count, processed_count))
 }
 
 // Parallel substring removal - processes batches of sequences in parallel
-fn remove_substrings_parallel(input_file: &str, output_file: Option<&str>, is_dna: bool, num_cores: usize) -> Result<()> {
-    eprintln!("[{}] Starting parallel substring removal using {} cores", timestamp(), num_cores);
-    eprintln!("[{}] Warning: Substring removal on large files requires significant memory and time", timestamp());
-    
-    // Set rayon thread pool size
+#[allow(clippy::too_many_arguments)]
+fn remove_substrings_parallel(
+    input_file: &str,
+    output_file: Option<&str>,
+    is_dna: bool,
+    num_cores: usize,
+    format: Format,
+    report_path: Option<&str>,
+) -> Result<()> {
+    let start = Instant::now();
+    eprintln!("[{}] Starting substring removal via suffix automaton ({} cores for sorting)", timestamp(), num_cores);
+
+    // Set rayon thread pool size; the automaton build itself is an inherently
+    // sequential dependency chain, but sorting by length below still benefits.
     rayon::ThreadPoolBuilder::new()
         .num_threads(num_cores)
         .build_global()
         .expect("Failed to set thread pool size");
-    
+
+    let format = resolve_format(input_file, format)?;
+
     // Read all sequences (unavoidable for substring checking)
+    let reader = open_input(input_file)?;
+    let mut record_reader = RecordReader::new(reader, format);
+
     let mut records = Vec::new();
-    let file = File::open(input_file)?;
-    let reader = BufReader::new(file);
-    
-    let mut current_header = String::new();
-    let mut current_sequence = String::new();
-    
-    for line in reader.lines() {
-        let line = line?;
-        let line = line.trim();
-        
-        if line.starts_with('>') {
-            if !current_header.is_empty() {
-                records.push(FastaRecord {
-                    header: current_header.clone(),
-                    sequence: current_sequence.clone(),
-                });
-            }
-            current_header = line.to_string();
-            current_sequence.clear();
-        } else if !line.is_empty() {
-            current_sequence.push_str(line);
-        }
+    while let Some(record) = record_reader.next_record()? {
+        records.push(record);
     }
-    
-    if !current_header.is_empty() {
-        records.push(FastaRecord {
-            header: current_header,
-            sequence: current_sequence,
-        });
-    }
-    
-    eprintln!("[{}] Loaded {} sequences for substring checking, {}", 
+
+    eprintln!("[{}] Loaded {} sequences for substring checking, {}",
              timestamp(), records.len(), get_memory_usage());
-    
+
     // Sort by length (longest first)
-    records.sort_by(|a, b| b.sequence.len().cmp(&a.sequence.len()));
-    
-    // Process sequences in parallel batches
-    let batch_size = (records.len() / num_cores).max(1000); // At least 1000 per batch
-    let kept_sequences = Arc::new(Mutex::new(HashSet::<String>::new()));
-    let final_records = Arc::new(Mutex::new(Vec::new()));
-    
-    eprintln!("[{}] Processing in batches of {} sequences", timestamp(), batch_size);
-    
-    // Process records in chunks using parallel iteration
-    records
-        .par_chunks(batch_size)
-        .enumerate()
-        .for_each(|(chunk_idx, chunk)| {
-            let mut local_kept: Vec<String> = Vec::new();
-            
-            for record in chunk {
-                let current_seq = &record.sequence;
-                let mut is_substring = false;
-                
-                // Check against globally kept sequences
-                {
-                    let kept_set = kept_sequences.lock().unwrap();
-                    for kept_seq in kept_set.iter() {
-                        if kept_seq != current_seq && kept_seq.contains(current_seq) {
-                            is_substring = true;
-                            break;
-                        }
-                        
-                        if is_dna {
-                            let rev_comp = reverse_complement(current_seq);
-                            if kept_seq.contains(&rev_comp) {
-                                is_substring = true;
-                                break;
-                            }
-                        }
-                    }
-                }
-                
-                if !is_substring {
-                    // Check against locally kept sequences in this batch
-                    for local_seq in &local_kept {
-                        if local_seq != current_seq && local_seq.contains(current_seq) {
-                            is_substring = true;
-                            break;
-                        }
-                        
-                        if is_dna {
-                            let rev_comp = reverse_complement(current_seq);
-                            if local_seq.contains(&rev_comp) {
-                                is_substring = true;
-                                break;
-                            }
-                        }
-                    }
-                }
-                
-                if !is_substring {
-                    local_kept.push(current_seq.clone());
-                    
-                    // Add to final results
-                    {
-                        let mut final_vec = final_records.lock().unwrap();
-                        final_vec.push(record.clone());
-                    }
-                }
+    records.par_sort_by_key(|record| std::cmp::Reverse(record.sequence.len()));
+
+    // Walk records longest-first: a sequence is dropped as soon as it's a
+    // substring of anything kept so far, and otherwise gets folded into the
+    // automaton so later (shorter-or-equal) sequences can match against it.
+    let mut automaton = SuffixAutomaton::new();
+    let mut final_records = Vec::new();
+    let mut report = report_path.map(|_| Report::new());
+    let total = records.len();
+
+    for (i, record) in records.into_iter().enumerate() {
+        let current_seq = &record.sequence;
+        let rev_comp = is_dna.then(|| reverse_complement(current_seq));
+
+        let mut owner = automaton.find_owner(current_seq);
+        if owner.is_none() {
+            if let Some(rev_comp) = &rev_comp {
+                owner = automaton.find_owner(rev_comp);
             }
-            
-            // Add local kept sequences to global set
-            {
-                let mut kept_set = kept_sequences.lock().unwrap();
-                for seq in local_kept {
-                    kept_set.insert(seq);
-                }
+        }
+
+        if let Some(representative) = owner {
+            if let Some(report) = report.as_mut() {
+                report.removed(&record.header, "substring", representative);
+            }
+        } else {
+            automaton.insert(current_seq, &record.header);
+            if let Some(rev_comp) = &rev_comp {
+                automaton.insert(rev_comp, &record.header);
             }
-            
-            if chunk_idx % 10 == 0 {
-                let final_count = final_records.lock().unwrap().len();
-                eprintln!("[{}] Processed chunk {}, {} sequences kept so far, {}", 
-                         timestamp(), chunk_idx, final_count, get_memory_usage());
+            if let Some(report) = report.as_mut() {
+                report.kept(&record.header);
             }
-        });
-    
+            final_records.push(record);
+        }
+
+        if i % 100000 == 0 {
+            eprintln!("[{}] Checked {}/{} sequences, {} kept so far, {}",
+                     timestamp(), i, total, final_records.len(), get_memory_usage());
+        }
+    }
+
     // Write results
-    let final_vec = final_records.lock().unwrap();
-    eprintln!("[{}] Writing {} final sequences to output", timestamp(), final_vec.len());
-    
+    eprintln!("[{}] Writing {} final sequences to output", timestamp(), final_records.len());
+
     let mut output_writer: Box<dyn Write> = if let Some(output_path) = output_file {
-        Box::new(File::create(output_path)?)
+        create_output(output_path)?
     } else {
         Box::new(std::io::stdout())
     };
-    
-    for record in final_vec.iter() {
-        writeln!(output_writer, "{}", record.header)?;
-        writeln!(output_writer, "{}", record.sequence)?;
+
+    for record in &final_records {
+        write_record(&mut output_writer, record)?;
     }
-    
+
+    if let (Some(report), Some(report_path)) = (&report, report_path) {
+        let summary = Summary {
+            total_input: total,
+            total_kept: final_records.len(),
+            total_removed: total - final_records.len(),
+            memory_usage: get_memory_usage(),
+            elapsed_secs: start.elapsed().as_secs_f64(),
+        };
+        report.write(report_path, &summary)?;
+        eprintln!("[{}] Wrote dedup report to {}", timestamp(), report_path);
+    }
+
     Ok(())
 }
 
 fn main() -> Result<()> {
     let args = Args::parse();
     let batch_size = args.batch_size.unwrap_or(10000);
-    
+
     // Determine number of cores to use
     let available_cores = num_cpus::get();
     let num_cores = args.cores.unwrap_or(available_cores / 2).max(1);
-    
+
     eprintln!("[{}] Starting seqdedupe (optimized) with batch size {}", timestamp(), batch_size);
     eprintln!("[{}] Available cores: {}, using: {}", timestamp(), available_cores, num_cores);
     eprintln!("[{}] Initial {}", timestamp(), get_memory_usage());
-    
+
     if args.substring {
         // For substring removal, use parallel processing
-        remove_substrings_parallel(&args.input, args.output.as_deref(), args.dna, num_cores)?;
+        remove_substrings_parallel(&args.input, args.output.as_deref(), args.dna, num_cores, args.format, args.report.as_deref())?;
     } else {
         // Use streaming approach for exact duplicates only
-        process_streaming_duplicates(&args.input, args.output.as_deref(), args.dna, batch_size)?;
+        process_streaming_duplicates(&args.input, args.output.as_deref(), args.dna, batch_size, args.format, args.verify, args.canonical, args.report.as_deref())?;
     }
-    
+
     eprintln!("[{}] Complete. Final {}", timestamp(), get_memory_usage());
-    
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,172 @@
+use anyhow::{Context, Result};
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+enum Status {
+    Kept,
+    Removed { reason: String, representative: String },
+}
+
+struct Entry {
+    header: String,
+    status: Status,
+}
+
+/// Final counts plus the resident memory (as `get_memory_usage` reports it)
+/// and the elapsed run time in seconds, taken at the end of the run, so a
+/// downstream script doesn't have to scrape logs for them.
+pub struct Summary {
+    pub total_input: usize,
+    pub total_kept: usize,
+    pub total_removed: usize,
+    pub memory_usage: String,
+    pub elapsed_secs: f64,
+}
+
+/// Accumulates one entry per input record (kept, or removed with a reason
+/// and the header of the representative it collapsed into) and writes it out
+/// as TSV or JSON, chosen by the report path's extension.
+pub struct Report {
+    entries: Vec<Entry>,
+}
+
+impl Report {
+    pub fn new() -> Self {
+        Report { entries: Vec::new() }
+    }
+
+    pub fn kept(&mut self, header: &str) {
+        self.entries.push(Entry {
+            header: header.to_string(),
+            status: Status::Kept,
+        });
+    }
+
+    pub fn removed(&mut self, header: &str, reason: &str, representative: &str) {
+        self.entries.push(Entry {
+            header: header.to_string(),
+            status: Status::Removed {
+                reason: reason.to_string(),
+                representative: representative.to_string(),
+            },
+        });
+    }
+
+    pub fn write(&self, path: &str, summary: &Summary) -> Result<()> {
+        let is_json = Path::new(path).extension().and_then(|ext| ext.to_str()) == Some("json");
+        let mut file =
+            File::create(path).with_context(|| format!("Failed to create report file: {}", path))?;
+
+        if is_json {
+            self.write_json(&mut file, summary)
+        } else {
+            self.write_tsv(&mut file, summary)
+        }
+    }
+
+    fn write_tsv(&self, file: &mut File, summary: &Summary) -> Result<()> {
+        writeln!(file, "header\tstatus\treason\trepresentative")?;
+        for entry in &self.entries {
+            match &entry.status {
+                Status::Kept => writeln!(file, "{}\tkept\t\t", escape_tsv_field(&entry.header))?,
+                Status::Removed { reason, representative } => writeln!(
+                    file,
+                    "{}\tremoved\t{}\t{}",
+                    escape_tsv_field(&entry.header),
+                    escape_tsv_field(reason),
+                    escape_tsv_field(representative)
+                )?,
+            }
+        }
+        writeln!(
+            file,
+            "# total_input={} total_kept={} total_removed={} memory={} elapsed_secs={:.3}",
+            summary.total_input,
+            summary.total_kept,
+            summary.total_removed,
+            summary.memory_usage,
+            summary.elapsed_secs
+        )?;
+        Ok(())
+    }
+
+    fn write_json(&self, file: &mut File, summary: &Summary) -> Result<()> {
+        writeln!(file, "{{")?;
+        writeln!(file, "  \"records\": [")?;
+        for (i, entry) in self.entries.iter().enumerate() {
+            let comma = if i + 1 == self.entries.len() { "" } else { "," };
+            match &entry.status {
+                Status::Kept => writeln!(
+                    file,
+                    "    {{\"header\": {}, \"status\": \"kept\"}}{}",
+                    json_escape(&entry.header), comma
+                )?,
+                Status::Removed { reason, representative } => writeln!(
+                    file,
+                    "    {{\"header\": {}, \"status\": \"removed\", \"reason\": {}, \"representative\": {}}}{}",
+                    json_escape(&entry.header), json_escape(reason), json_escape(representative), comma
+                )?,
+            }
+        }
+        writeln!(file, "  ],")?;
+        writeln!(
+            file,
+            "  \"summary\": {{\"total_input\": {}, \"total_kept\": {}, \"total_removed\": {}, \"memory\": {}, \"elapsed_secs\": {:.3}}}",
+            summary.total_input, summary.total_kept, summary.total_removed, json_escape(&summary.memory_usage), summary.elapsed_secs
+        )?;
+        writeln!(file, "}}")?;
+        Ok(())
+    }
+}
+
+/// Escapes a field for TSV: the format only has one hope of staying aligned
+/// if its own column separator and line terminators never appear literally
+/// inside a field, so tabs/CR/LF in a header or reason are backslash-escaped
+/// rather than passed through.
+fn escape_tsv_field(field: &str) -> String {
+    field.replace('\\', "\\\\").replace('\t', "\\t").replace('\r', "\\r").replace('\n', "\\n")
+}
+
+/// Escapes a string for JSON, unlike `{:?}` Debug formatting this always
+/// produces valid JSON: control characters are emitted as 4-hex-digit
+/// `\u00XX` escapes rather than Rust's `\u{XX}` debug form, which `json.load`
+/// and friends reject.
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_tsv_field_escapes_tabs_and_newlines() {
+        assert_eq!(escape_tsv_field("seq\t1"), "seq\\t1");
+        assert_eq!(escape_tsv_field("line1\nline2"), "line1\\nline2");
+        assert_eq!(escape_tsv_field("a\\b"), "a\\\\b");
+        assert_eq!(escape_tsv_field("plain"), "plain");
+    }
+
+    #[test]
+    fn json_escape_quotes_and_escapes_control_characters() {
+        assert_eq!(json_escape("plain"), "\"plain\"");
+        assert_eq!(json_escape("has \"quotes\""), "\"has \\\"quotes\\\"\"");
+        assert_eq!(json_escape("tab\there"), "\"tab\\there\"");
+        assert_eq!(json_escape("bell\u{0007}"), "\"bell\\u0007\"");
+    }
+}
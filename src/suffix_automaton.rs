@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+
+/// A generalized suffix automaton over a growing set of strings, used to test
+/// "is `query` a substring of any string inserted so far?" in O(|query|)
+/// instead of the O(kept x query x len) `str::contains` scan it replaces.
+///
+/// Each state holds the length of its longest member string, a suffix link,
+/// a character -> state transition map, and the owner of the string whose
+/// insertion created it (a clone keeps the owner of the state it was split
+/// from), per the standard SAM construction; `insert` extends
+/// the automaton with one more string using the generalized (multi-string)
+/// extend step, which reuses an existing transition from the root when one
+/// already matches instead of always creating a fresh state.
+pub struct SuffixAutomaton {
+    states: Vec<State>,
+    last: usize,
+}
+
+struct State {
+    len: usize,
+    link: Option<usize>,
+    transitions: HashMap<char, usize>,
+    /// The owner passed to the `insert` call that created this state, or, for
+    /// a clone, the owner of the state it was split from. Since a
+    /// transition only ever exists because some inserted string walked
+    /// through it at construction time, following transitions for `query`
+    /// from the root and landing on a state guarantees that state's owner
+    /// contains `query` as a substring -- letting `find_owner` answer "whose
+    /// sequence is this a substring of?" in the same walk used to test
+    /// membership, instead of a separate O(kept) scan.
+    owner: Option<String>,
+}
+
+const ROOT: usize = 0;
+
+impl SuffixAutomaton {
+    pub fn new() -> Self {
+        SuffixAutomaton {
+            states: vec![State {
+                len: 0,
+                link: None,
+                transitions: HashMap::new(),
+                owner: None,
+            }],
+            last: ROOT,
+        }
+    }
+
+    /// Returns the owner of a string already inserted that contains `query`
+    /// as a substring, or `None` if no inserted string does.
+    pub fn find_owner(&self, query: &str) -> Option<&str> {
+        if query.is_empty() {
+            // The empty string is a substring of every inserted string, but
+            // the root has no owner: answer with the first state created,
+            // which always belongs to the first non-empty inserted string.
+            return self.states.get(1).and_then(|state| state.owner.as_deref());
+        }
+        let state = self.walk(query)?;
+        self.states[state].owner.as_deref()
+    }
+
+    fn walk(&self, query: &str) -> Option<usize> {
+        let mut state = ROOT;
+        for c in query.chars() {
+            match self.states[state].transitions.get(&c) {
+                Some(&next) => state = next,
+                None => return None,
+            }
+        }
+        Some(state)
+    }
+
+    /// Adds `s` as a new member string of the automaton, attributed to `owner`.
+    pub fn insert(&mut self, s: &str, owner: &str) {
+        self.last = ROOT;
+        for c in s.chars() {
+            self.extend(c, owner);
+        }
+    }
+
+    fn extend(&mut self, c: char, owner: &str) {
+        // Generalized-SAM fast path: `last` already has a transition on `c`
+        // from a previous string. If it lands exactly one character further
+        // than `last`, that state already represents "last's string + c" and
+        // can be reused as-is; otherwise it needs the usual clone treatment.
+        if let Some(&q) = self.states[self.last].transitions.get(&c) {
+            if self.states[q].len == self.states[self.last].len + 1 {
+                self.last = q;
+            } else {
+                let clone = self.clone_state(q, self.states[self.last].len + 1);
+                self.redirect_transitions(self.last, c, q, clone);
+                self.states[q].link = Some(clone);
+                self.last = clone;
+            }
+            return;
+        }
+
+        let cur = self.new_state(self.states[self.last].len + 1, Some(owner.to_string()));
+        let mut p = Some(self.last);
+        while let Some(pi) = p {
+            if self.states[pi].transitions.contains_key(&c) {
+                break;
+            }
+            self.states[pi].transitions.insert(c, cur);
+            p = self.states[pi].link;
+        }
+
+        match p {
+            None => self.states[cur].link = Some(ROOT),
+            Some(pi) => {
+                let q = self.states[pi].transitions[&c];
+                if self.states[pi].len + 1 == self.states[q].len {
+                    self.states[cur].link = Some(q);
+                } else {
+                    let clone = self.clone_state(q, self.states[pi].len + 1);
+                    self.states[q].link = Some(clone);
+                    self.states[cur].link = Some(clone);
+                    self.redirect_transitions(pi, c, q, clone);
+                }
+            }
+        }
+        self.last = cur;
+    }
+
+    fn new_state(&mut self, len: usize, owner: Option<String>) -> usize {
+        self.states.push(State {
+            len,
+            link: None,
+            transitions: HashMap::new(),
+            owner,
+        });
+        self.states.len() - 1
+    }
+
+    /// Clones `from` into a new state of length `len`. The clone inherits
+    /// `from`'s owner: it represents the shorter suffixes of `from`'s strings,
+    /// which are substrings of the same string that created `from`, so that
+    /// string remains a valid answer for anything that resolves to the clone.
+    fn clone_state(&mut self, from: usize, len: usize) -> usize {
+        let transitions = self.states[from].transitions.clone();
+        let link = self.states[from].link;
+        let owner = self.states[from].owner.clone();
+        self.states.push(State { len, link, transitions, owner });
+        self.states.len() - 1
+    }
+
+    /// Walks suffix links from `start` redirecting the `c` transition away
+    /// from `target` to `replacement`, stopping as soon as a state no longer
+    /// points at `target` (the clone step of the standard SAM construction).
+    fn redirect_transitions(&mut self, start: usize, c: char, target: usize, replacement: usize) {
+        let mut p = Some(start);
+        while let Some(pi) = p {
+            if self.states[pi].transitions.get(&c) == Some(&target) {
+                self.states[pi].transitions.insert(c, replacement);
+                p = self.states[pi].link;
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_automaton_contains_nothing() {
+        let automaton = SuffixAutomaton::new();
+        assert_eq!(automaton.find_owner("a"), None);
+    }
+
+    #[test]
+    fn finds_substrings_of_a_single_inserted_string() {
+        let mut automaton = SuffixAutomaton::new();
+        automaton.insert("banana", "seq1");
+
+        for substring in ["banana", "ban", "nan", "ana", "a", "n"] {
+            assert_eq!(automaton.find_owner(substring), Some("seq1"), "expected {substring:?} to match");
+        }
+        assert_eq!(automaton.find_owner("banas"), None);
+        assert_eq!(automaton.find_owner("xyz"), None);
+    }
+
+    #[test]
+    fn tie_length_fast_path_reuses_existing_state() {
+        // Inserting "ab" then "abc" extends the same chain one character at a
+        // time, so the fast path in `extend` that reuses an existing
+        // transition (rather than cloning) is exercised on the second insert.
+        let mut automaton = SuffixAutomaton::new();
+        automaton.insert("ab", "seq1");
+        automaton.insert("abc", "seq2");
+
+        assert!(automaton.find_owner("ab").is_some());
+        assert_eq!(automaton.find_owner("abc"), Some("seq2"));
+        assert!(automaton.find_owner("bc").is_some());
+        assert_eq!(automaton.find_owner("abcd"), None);
+    }
+
+    #[test]
+    fn generalized_multi_string_clone_splits_shared_prefix() {
+        // "abcbc" builds a plain SAM. Inserting "bcb" then follows the
+        // existing transitions for "bc" via the tie-length fast path, but its
+        // final "b" transition leads to the longer "abcb" state, forcing the
+        // generalized-SAM clone-on-mismatch path in `extend`.
+        let mut automaton = SuffixAutomaton::new();
+        automaton.insert("abcbc", "seq1");
+        automaton.insert("bcb", "seq2");
+
+        for substring in ["abcbc", "bcbc", "cbc", "bc", "c", "bcb", "cb", "b"] {
+            assert!(automaton.find_owner(substring).is_some(), "expected {substring:?} to match");
+        }
+        assert_eq!(automaton.find_owner("abcbcd"), None);
+        assert_eq!(automaton.find_owner("xyz"), None);
+    }
+
+    #[test]
+    fn find_owner_resolves_which_string_was_absorbed() {
+        let mut automaton = SuffixAutomaton::new();
+        automaton.insert("AAATTT", "seq1");
+        automaton.insert("GGCCC", "seq2");
+
+        assert_eq!(automaton.find_owner("AAT"), Some("seq1"));
+        assert_eq!(automaton.find_owner("GCC"), Some("seq2"));
+        assert_eq!(automaton.find_owner("TTA"), None);
+    }
+
+    #[test]
+    fn dna_forward_and_reverse_complement_are_independent_strings() {
+        // Callers insert a DNA sequence and its reverse complement as two
+        // separate strings under the same owner; both orientations should
+        // independently be queryable as substrings.
+        let mut automaton = SuffixAutomaton::new();
+        automaton.insert("ACGT", "seq1");
+        automaton.insert("ACGT", "seq1"); // reverse complement of ACGT is ACGT
+
+        assert_eq!(automaton.find_owner("ACGT"), Some("seq1"));
+        assert!(automaton.find_owner("CGT").is_some());
+        assert!(automaton.find_owner("ACG").is_some());
+
+        let mut automaton2 = SuffixAutomaton::new();
+        automaton2.insert("AAAGGG", "seq2");
+        automaton2.insert("CCCTTT", "seq2"); // reverse complement of AAAGGG
+
+        assert_eq!(automaton2.find_owner("AAAGGG"), Some("seq2"));
+        assert_eq!(automaton2.find_owner("CCCTTT"), Some("seq2"));
+        assert_eq!(automaton2.find_owner("AAACCC"), None);
+    }
+}